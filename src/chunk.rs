@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::io_engine::open_mmap;
+use crate::parse::{Weather, parse};
+
+type ChunkHash = [u8; 32];
+type StationRows = Vec<(Vec<u8>, Weather)>;
+
+const fn gear_table() -> [u64; 256] {
+    // Deterministic splitmix64 stream; just needs to be well-mixed, not random.
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        table[i] = z;
+        i += 1;
+    }
+    table
+}
+
+const GEAR: [u64; 256] = gear_table();
+
+const MIN_CHUNK: usize = 512 * 1024;
+const AVG_CHUNK: usize = 2 * 1024 * 1024;
+const MAX_CHUNK: usize = 8 * 1024 * 1024;
+
+const fn mask_with_bits(bits: u32) -> u64 {
+    (1u64 << bits) - 1
+}
+
+// Normalized chunking: stricter mask below the target size, looser above it.
+const MASK_SMALL: u64 = mask_with_bits(15);
+const MASK_LARGE: u64 = mask_with_bits(11);
+
+/// Finds the next content-defined cut point in `data`, relative to `data[0]`.
+fn find_cut(data: &[u8]) -> usize {
+    let len = data.len();
+    if len <= MIN_CHUNK {
+        return len;
+    }
+
+    let mut fp: u64 = 0;
+    let small_end = AVG_CHUNK.min(len);
+    let mut i = MIN_CHUNK;
+    while i < small_end {
+        fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+        if fp & MASK_SMALL == 0 {
+            return i + 1;
+        }
+        i += 1;
+    }
+
+    let large_end = MAX_CHUNK.min(len);
+    while i < large_end {
+        fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+        if fp & MASK_LARGE == 0 {
+            return i + 1;
+        }
+        i += 1;
+    }
+    large_end
+}
+
+/// Pushes a gear-hash cut point forward to the next newline so chunks stay
+/// line-aligned and can be handed straight to `parse`.
+fn snap_to_newline(data: &[u8], cut: usize) -> usize {
+    if cut >= data.len() {
+        return data.len();
+    }
+    match data[cut..].iter().position(|&b| b == b'\n') {
+        Some(offset) => cut + offset + 1,
+        None => data.len(),
+    }
+}
+
+fn chunk_boundaries(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut bounds = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let cut = snap_to_newline(data, start + find_cut(&data[start..]));
+        bounds.push((start, cut));
+        start = cut;
+    }
+    bounds
+}
+
+fn take<'a>(buf: &'a [u8], pos: &mut usize, n: usize) -> &'a [u8] {
+    let slice = &buf[*pos..*pos + n];
+    *pos += n;
+    slice
+}
+
+fn load_cache(path: &str) -> BTreeMap<ChunkHash, StationRows> {
+    let Ok(buf) = std::fs::read(path) else {
+        return BTreeMap::new();
+    };
+
+    let mut pos = 0;
+    let count = u64::from_le_bytes(take(&buf, &mut pos, 8).try_into().unwrap());
+    let mut cache = BTreeMap::new();
+    for _ in 0..count {
+        let hash: ChunkHash = take(&buf, &mut pos, 32).try_into().unwrap();
+        let num_stations = u32::from_le_bytes(take(&buf, &mut pos, 4).try_into().unwrap());
+        let mut stations = Vec::with_capacity(num_stations as usize);
+        for _ in 0..num_stations {
+            let name_len = u16::from_le_bytes(take(&buf, &mut pos, 2).try_into().unwrap()) as usize;
+            let name = take(&buf, &mut pos, name_len).to_vec();
+            let total = u32::from_le_bytes(take(&buf, &mut pos, 4).try_into().unwrap());
+            let min = i16::from_le_bytes(take(&buf, &mut pos, 2).try_into().unwrap());
+            let max = i16::from_le_bytes(take(&buf, &mut pos, 2).try_into().unwrap());
+            let sum = i64::from_le_bytes(take(&buf, &mut pos, 8).try_into().unwrap());
+            stations.push((
+                name,
+                Weather {
+                    total,
+                    min,
+                    max,
+                    sum,
+                },
+            ));
+        }
+        cache.insert(hash, stations);
+    }
+    cache
+}
+
+fn save_cache(path: &str, entries: &BTreeMap<ChunkHash, StationRows>) {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (hash, stations) in entries {
+        buf.extend_from_slice(hash);
+        buf.extend_from_slice(&(stations.len() as u32).to_le_bytes());
+        for (name, weather) in stations {
+            buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            buf.extend_from_slice(name);
+            buf.extend_from_slice(&weather.total.to_le_bytes());
+            buf.extend_from_slice(&weather.min.to_le_bytes());
+            buf.extend_from_slice(&weather.max.to_le_bytes());
+            buf.extend_from_slice(&weather.sum.to_le_bytes());
+        }
+    }
+    std::fs::write(path, buf).unwrap();
+}
+
+/// Parses `path`, reusing cached partial aggregates from `<path>.cdc-cache` for
+/// any chunk whose content hash is already known.
+pub fn run_cached(path: &str) -> BTreeMap<Vec<u8>, Weather> {
+    let data = open_mmap(path);
+    let bounds = chunk_boundaries(data);
+
+    let cache_path = format!("{path}.cdc-cache");
+    let cache = load_cache(&cache_path);
+
+    // One entry per chunk *occurrence*, not deduped by hash: two chunks can share
+    // a hash (repeated/periodic content) and both still need to contribute.
+    let chunks: Vec<(ChunkHash, StationRows)> = bounds
+        .into_par_iter()
+        .map(|(start, end)| {
+            let bytes = &data[start..end];
+            let hash = *blake3::hash(bytes).as_bytes();
+            let stations = match cache.get(&hash) {
+                Some(cached) => cached.clone(),
+                None => parse(bytes)
+                    .into_iter()
+                    .map(|(name, weather)| (name.to_vec(), weather))
+                    .collect(),
+            };
+            (hash, stations)
+        })
+        .collect();
+
+    let mut merged: BTreeMap<Vec<u8>, Weather> = BTreeMap::new();
+    for (_, stations) in &chunks {
+        for (name, weather) in stations {
+            merged
+                .entry(name.clone())
+                .and_modify(|entry| {
+                    entry.total += weather.total;
+                    entry.min = entry.min.min(weather.min);
+                    entry.max = entry.max.max(weather.max);
+                    entry.sum += weather.sum;
+                })
+                .or_insert(*weather);
+        }
+    }
+
+    // The sidecar only needs one partial aggregate per distinct chunk, so dedup
+    // by hash here, after the (non-deduped) merge above.
+    save_cache(&cache_path, &chunks.into_iter().collect());
+    merged
+}
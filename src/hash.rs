@@ -1,47 +1,158 @@
+use std::hash::Hasher;
 use std::ops::BitXor;
 
-const SEED: u64 = 0x517cc1b727220a95;
+const PRIME1: u64 = 0x9E3779B185EBCA87;
+const PRIME2: u64 = 0xC2B2AE3D27D4EB4F;
+const PRIME3: u64 = 0x165667B19E3779F9;
 
+/// xxHash-style rolling hasher: each lane is folded in with `rotl(.., 31) * PRIME1`,
+/// and `finish()` runs the full avalanche so the low bits `FlatTable` indexes on are
+/// well distributed, not just the high bits.
 pub struct MyHasher {
     state: u64,
 }
 
-impl std::hash::Hasher for MyHasher {
+impl Hasher for MyHasher {
     #[inline(always)]
     fn finish(&self) -> u64 {
-        self.state
+        let mut h = self.state;
+        h ^= h >> 33;
+        h = h.wrapping_mul(PRIME2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(PRIME3);
+        h ^= h >> 32;
+        h
     }
 
     #[inline(always)]
     fn write(&mut self, mut bytes: &[u8]) {
         while bytes.len() >= 8 {
             let n = u64::from_ne_bytes(bytes[..8].try_into().unwrap());
-            self.state = self.state.bitxor(n).wrapping_mul(SEED);
+            self.state = self.state.bitxor(n).rotate_left(31).wrapping_mul(PRIME1);
             bytes = &bytes[8..];
         }
 
         if bytes.len() >= 4 {
             let n = u32::from_ne_bytes(bytes[..4].try_into().unwrap());
-            self.state = self.state.bitxor(n as u64).wrapping_mul(SEED);
+            self.state = self
+                .state
+                .bitxor(n as u64)
+                .rotate_left(31)
+                .wrapping_mul(PRIME1);
             bytes = &bytes[4..];
         }
 
         for byte in bytes {
-            self.state = self.state.bitxor(*byte as u64).wrapping_mul(SEED);
+            self.state = self
+                .state
+                .bitxor(*byte as u64)
+                .rotate_left(31)
+                .wrapping_mul(PRIME1);
         }
     }
 }
 
-#[derive(Default)]
-pub struct MyHasherBuilder;
+impl MyHasher {
+    #[inline(always)]
+    fn hash(key: &[u8]) -> u64 {
+        let mut hasher = MyHasher { state: 0 };
+        hasher.write(key);
+        hasher.finish()
+    }
+}
 
-impl std::hash::BuildHasher for MyHasherBuilder {
-    type Hasher = MyHasher;
+type Slot<'a, V> = Option<(&'a [u8], V)>;
 
-    #[inline(always)]
-    fn build_hasher(&self) -> MyHasher {
-        MyHasher { state: 0 }
+/// Flat, open-addressing table keyed by borrowed byte slices, used in place of
+/// `std::collections::HashMap` on the aggregation hot path: linear probing on a
+/// power-of-two capacity, keys compared on probe-hit.
+pub struct FlatTable<'a, V> {
+    slots: Box<[Slot<'a, V>]>,
+    mask: usize,
+    len: usize,
+}
+
+impl<'a, V> FlatTable<'a, V> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.next_power_of_two().max(16);
+        Self {
+            slots: (0..capacity).map(|_| None).collect(),
+            mask: capacity - 1,
+            len: 0,
+        }
+    }
+
+    fn probe_index(&self, key: &[u8]) -> usize {
+        MyHasher::hash(key) as usize & self.mask
+    }
+
+    fn grow(&mut self) {
+        let mut grown = Self::with_capacity(self.slots.len() * 2);
+        for (key, value) in std::mem::take(&mut self.slots).into_vec().into_iter().flatten() {
+            let mut idx = grown.probe_index(key);
+            while grown.slots[idx].is_some() {
+                idx = (idx + 1) & grown.mask;
+            }
+            grown.slots[idx] = Some((key, value));
+        }
+        grown.len = self.len;
+        *self = grown;
+    }
+}
+
+impl<'a, V: Default> FlatTable<'a, V> {
+    /// Returns the slot for `key`, inserting `V::default()` first if absent.
+    pub fn entry_or_default(&mut self, key: &'a [u8]) -> &mut V {
+        if (self.len + 1) * 2 > self.slots.len() {
+            self.grow();
+        }
+
+        let mut idx = self.probe_index(key);
+        loop {
+            match self.slots[idx] {
+                Some((k, _)) if k == key => break,
+                None => {
+                    self.slots[idx] = Some((key, V::default()));
+                    self.len += 1;
+                    break;
+                }
+                _ => idx = (idx + 1) & self.mask,
+            }
+        }
+        &mut self.slots[idx].as_mut().unwrap().1
+    }
+}
+
+impl<'a, V> Default for FlatTable<'a, V> {
+    fn default() -> Self {
+        Self::with_capacity(16)
+    }
+}
+
+pub struct FlatTableIntoIter<'a, V> {
+    inner: std::vec::IntoIter<Slot<'a, V>>,
+}
+
+impl<'a, V> Iterator for FlatTableIntoIter<'a, V> {
+    type Item = (&'a [u8], V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for slot in self.inner.by_ref() {
+            if slot.is_some() {
+                return slot;
+            }
+        }
+        None
     }
 }
 
-pub type MyHashMap<K, V> = std::collections::HashMap<K, V, MyHasherBuilder>;
+impl<'a, V> IntoIterator for FlatTable<'a, V> {
+    type Item = (&'a [u8], V);
+    type IntoIter = FlatTableIntoIter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        FlatTableIntoIter {
+            inner: self.slots.into_vec().into_iter(),
+        }
+    }
+}
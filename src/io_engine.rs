@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::os::fd::AsRawFd;
+
+use io_uring::{IoUring, opcode, types};
+
+/// How the input file is turned into byte buffers handed to the caller.
+pub trait IoEngine {
+    fn for_each_chunk(&self, path: &str, on_chunk: &mut dyn FnMut(&[u8]));
+}
+
+/// mmap + `MADV_SEQUENTIAL`, parsed as one contiguous slice.
+pub struct SyncIoEngine;
+
+impl IoEngine for SyncIoEngine {
+    fn for_each_chunk(&self, path: &str, on_chunk: &mut dyn FnMut(&[u8])) {
+        let bytes = open_mmap(path);
+        on_chunk(bytes);
+    }
+}
+
+pub(crate) fn open_mmap(file: &str) -> &'static [u8] {
+    let file = File::open(file).unwrap();
+    let len = file.metadata().unwrap().len();
+    unsafe {
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            len as libc::size_t,
+            libc::PROT_READ,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            panic!("{:?}", std::io::Error::last_os_error());
+        }
+        if libc::madvise(ptr, len as libc::size_t, libc::MADV_SEQUENTIAL) != 0 {
+            panic!("{:?}", std::io::Error::last_os_error());
+        }
+        std::slice::from_raw_parts(ptr as *const u8, len as usize)
+    }
+}
+
+/// Number of reads the ring is allowed to have outstanding at once.
+const MAX_CONCURRENT_IO: usize = 4;
+/// Size of each read request.
+const READ_SIZE: usize = 4 * 1024 * 1024;
+
+/// io_uring-based streaming reader. Keeps several fixed-size reads in flight and
+/// hands buffers to `on_chunk` in file order as they complete.
+pub struct AsyncIoEngine {
+    pub max_concurrent_io: usize,
+}
+
+impl AsyncIoEngine {
+    pub fn new(max_concurrent_io: usize) -> Self {
+        Self { max_concurrent_io }
+    }
+}
+
+impl Default for AsyncIoEngine {
+    fn default() -> Self {
+        Self::new(MAX_CONCURRENT_IO)
+    }
+}
+
+struct InFlight {
+    buf: Vec<u8>,
+    offset: u64,
+    /// Bytes of `buf` already filled by completed reads; short reads resubmit
+    /// for the remainder instead of being treated as done.
+    filled: usize,
+}
+
+impl IoEngine for AsyncIoEngine {
+    fn for_each_chunk(&self, path: &str, on_chunk: &mut dyn FnMut(&[u8])) {
+        let file = File::open(path).unwrap();
+        let fd = types::Fd(file.as_raw_fd());
+        let len = file.metadata().unwrap().len();
+
+        let mut ring = IoUring::new(self.max_concurrent_io as u32).unwrap();
+        let mut slots: Vec<Option<InFlight>> = (0..self.max_concurrent_io).map(|_| None).collect();
+        // Tail bytes of the previous delivered buffer that didn't end on a newline;
+        // stitched onto the next buffer before it's handed to `on_chunk`.
+        let mut carry: Vec<u8> = Vec::new();
+
+        let mut next_offset: u64 = 0;
+        let mut in_flight = 0;
+
+        let submit = |ring: &mut IoUring, slots: &mut [Option<InFlight>], slot: usize, offset: u64| {
+            let this_len = READ_SIZE.min((len - offset) as usize);
+            let mut buf = vec![0u8; this_len];
+            let entry = opcode::Read::new(fd, buf.as_mut_ptr(), this_len as u32)
+                .offset(offset)
+                .build()
+                .user_data(slot as u64);
+            slots[slot] = Some(InFlight {
+                buf,
+                offset,
+                filled: 0,
+            });
+            unsafe { ring.submission().push(&entry).unwrap() };
+        };
+
+        // Resubmits a read for the unfilled tail of `inflight.buf`, used when a
+        // completion under-fills its request (a short read that isn't EOF).
+        let resubmit_tail = |ring: &mut IoUring, slot: usize, inflight: &mut InFlight| {
+            let remaining_offset = inflight.offset + inflight.filled as u64;
+            let remaining_len = (inflight.buf.len() - inflight.filled) as u32;
+            let entry = unsafe {
+                opcode::Read::new(
+                    fd,
+                    inflight.buf.as_mut_ptr().add(inflight.filled),
+                    remaining_len,
+                )
+                .offset(remaining_offset)
+                .build()
+                .user_data(slot as u64)
+            };
+            unsafe { ring.submission().push(&entry).unwrap() };
+        };
+
+        for slot in 0..self.max_concurrent_io {
+            if next_offset >= len {
+                break;
+            }
+            submit(&mut ring, &mut slots, slot, next_offset);
+            next_offset += READ_SIZE as u64;
+            in_flight += 1;
+        }
+        ring.submit().unwrap();
+
+        // Completions can arrive out of offset order (that's the point of keeping
+        // several reads in flight); buffer them here and only deliver to `on_chunk`
+        // once they're contiguous with the last delivered offset, so the carry-
+        // forward newline stitching always sees buffers in file order.
+        let mut pending: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+        let mut next_deliver_offset: u64 = 0;
+
+        let mut deliver = |buf: Vec<u8>, on_chunk: &mut dyn FnMut(&[u8])| {
+            let last_newline = buf.iter().rposition(|&b| b == b'\n');
+            let (complete, rest) = match last_newline {
+                Some(i) => buf.split_at(i + 1),
+                None => (&buf[..0], &buf[..]),
+            };
+
+            carry.extend_from_slice(complete);
+            let owned = std::mem::take(&mut carry).into_boxed_slice();
+            carry.extend_from_slice(rest);
+
+            if !owned.is_empty() {
+                on_chunk(Box::leak(owned));
+            }
+        };
+
+        while in_flight > 0 {
+            ring.submit_and_wait(1).unwrap();
+            let completed: Vec<(usize, i32)> = ring
+                .completion()
+                .map(|cqe| (cqe.user_data() as usize, cqe.result()))
+                .collect();
+
+            for (slot, result) in completed {
+                in_flight -= 1;
+                let mut inflight = slots[slot].take().unwrap();
+                if result < 0 {
+                    panic!(
+                        "io_uring read failed at offset {}: {:?}",
+                        inflight.offset,
+                        std::io::Error::from_raw_os_error(-result)
+                    );
+                }
+                inflight.filled += result as usize;
+
+                if inflight.filled < inflight.buf.len() {
+                    if result == 0 {
+                        panic!(
+                            "unexpected EOF at offset {}: expected {} more bytes",
+                            inflight.offset + inflight.filled as u64,
+                            inflight.buf.len() - inflight.filled
+                        );
+                    }
+                    resubmit_tail(&mut ring, slot, &mut inflight);
+                    slots[slot] = Some(inflight);
+                    in_flight += 1;
+                    ring.submit().unwrap();
+                    continue;
+                }
+
+                let InFlight { buf, offset, .. } = inflight;
+                pending.insert(offset, buf);
+
+                if next_offset < len {
+                    submit(&mut ring, &mut slots, slot, next_offset);
+                    next_offset += READ_SIZE as u64;
+                    in_flight += 1;
+                    ring.submit().unwrap();
+                }
+            }
+
+            while let Some(buf) = pending.remove(&next_deliver_offset) {
+                next_deliver_offset += READ_SIZE as u64;
+                deliver(buf, on_chunk);
+            }
+        }
+
+        if !carry.is_empty() {
+            on_chunk(Box::leak(carry.into_boxed_slice()));
+        }
+    }
+}
+
+/// Picks an engine from `--engine=<name>` or the `IO_ENGINE` env var (`mmap`, `uring`).
+pub fn select_engine() -> Box<dyn IoEngine> {
+    let from_arg = std::env::args()
+        .find_map(|arg| arg.strip_prefix("--engine=").map(str::to_owned));
+    let choice = from_arg
+        .or_else(|| std::env::var("IO_ENGINE").ok())
+        .unwrap_or_else(|| "mmap".to_string());
+
+    match choice.as_str() {
+        "uring" => Box::new(AsyncIoEngine::default()),
+        _ => Box::new(SyncIoEngine),
+    }
+}
@@ -2,34 +2,46 @@
 #![feature(iter_array_chunks)]
 #![feature(rustc_private)]
 
-use std::{collections::BTreeMap, fs::File, io::Write, os::fd::AsRawFd};
+use std::{collections::BTreeMap, io::Write};
 
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
+use crate::hash::FlatTable;
+use crate::io_engine::select_engine;
 use crate::parse::{Weather, parse};
 
+mod chunk;
 mod hash;
+mod io_engine;
 mod parse;
 
-fn open_reader(file: &str) -> &[u8] {
-    let file = File::open(file).unwrap();
-    let len = file.metadata().unwrap().len();
-    unsafe {
-        let ptr = libc::mmap(
-            std::ptr::null_mut(),
-            len as libc::size_t,
-            libc::PROT_READ,
-            libc::MAP_SHARED,
-            file.as_raw_fd(),
-            0,
-        );
-        if ptr == libc::MAP_FAILED {
-            panic!("{:?}", std::io::Error::last_os_error());
-        }
-        if libc::madvise(ptr, len as libc::size_t, libc::MADV_SEQUENTIAL) != 0 {
-            panic!("{:?}", std::io::Error::last_os_error());
-        }
-        std::slice::from_raw_parts(ptr as *const u8, len as usize)
+fn merge_weather_tables<'a>(
+    mut acc: FlatTable<'a, Weather>,
+    other: FlatTable<'a, Weather>,
+) -> FlatTable<'a, Weather> {
+    for (station, measurement) in other {
+        let entry = acc.entry_or_default(station);
+        entry.total += measurement.total;
+        entry.min = entry.min.min(measurement.min);
+        entry.max = entry.max.max(measurement.max);
+        entry.sum += measurement.sum;
+    }
+    acc
+}
+
+// `IoEngine::for_each_chunk`'s callback is `for<'r> FnMut(&'r [u8])`, so a
+// chunk's `bytes` can't outlive the call — station-name keys have to be copied
+// into the cross-chunk accumulator rather than borrowed from it.
+fn merge_into_owned(acc: &mut BTreeMap<Vec<u8>, Weather>, other: BTreeMap<&[u8], Weather>) {
+    for (station, measurement) in other {
+        acc.entry(station.to_vec())
+            .and_modify(|entry| {
+                entry.total += measurement.total;
+                entry.min = entry.min.min(measurement.min);
+                entry.max = entry.max.max(measurement.max);
+                entry.sum += measurement.sum;
+            })
+            .or_insert(measurement);
     }
 }
 
@@ -71,32 +83,19 @@ fn get_map_par(bytes: &[u8]) -> BTreeMap<&[u8], Weather> {
     blocks
         .into_par_iter()
         .map(|bytes| parse(bytes))
-        .reduce_with(|mut acc, other| {
-            for (station, measurement) in other {
-                acc.entry(station)
-                    .and_modify(|entry| {
-                        entry.total += measurement.total;
-                        entry.min = entry.min.min(measurement.min);
-                        entry.max = entry.max.max(measurement.max);
-                        entry.sum += measurement.sum;
-                    })
-                    .or_insert(measurement);
-            }
-            acc
-        })
+        .reduce_with(|acc, other| merge_weather_tables(acc, other))
         .unwrap()
         .into_iter()
         .collect::<BTreeMap<_, _>>()
 }
 
-fn main() {
-    let bytes = open_reader("data/measurements.txt");
-    let mut map = get_map_par(bytes).into_iter().peekable();
+fn print_result(map: impl IntoIterator<Item = (impl AsRef<[u8]>, Weather)>) {
+    let mut map = map.into_iter().peekable();
 
     let mut stdout = std::io::stdout().lock();
     stdout.write_all(b"{").unwrap();
     while let Some((id, stats)) = map.next() {
-        stdout.write_all(id).unwrap();
+        stdout.write_all(id.as_ref()).unwrap();
         write!(
             stdout,
             "={:.1}/{:.1}/{:.1}",
@@ -111,3 +110,21 @@ fn main() {
     }
     stdout.write_all(b"}\n").unwrap();
 }
+
+fn main() {
+    let path = "data/measurements.txt";
+
+    // Opt-in: reuse the chunk-hash sidecar cache across runs.
+    if std::env::var("CACHED").is_ok() {
+        print_result(chunk::run_cached(path));
+        return;
+    }
+
+    let engine = select_engine();
+
+    let mut map: BTreeMap<Vec<u8>, Weather> = BTreeMap::new();
+    engine.for_each_chunk(path, &mut |bytes| {
+        merge_into_owned(&mut map, get_map_par(bytes));
+    });
+    print_result(map);
+}
@@ -1,7 +1,8 @@
 use std::simd::{Mask, Simd, cmp::SimdPartialEq};
 
-use crate::hash::MyHashMap;
+use crate::hash::FlatTable;
 
+#[derive(Clone, Copy)]
 pub struct Weather {
     pub total: u32,
     pub min: i16,
@@ -9,6 +10,17 @@ pub struct Weather {
     pub sum: i64,
 }
 
+impl Default for Weather {
+    fn default() -> Self {
+        Weather {
+            total: 0,
+            min: i16::MAX,
+            max: i16::MIN,
+            sum: 0,
+        }
+    }
+}
+
 #[inline(always)]
 fn parse_temp(t: &[u8]) -> i16 {
     let t_len = t.len();
@@ -29,7 +41,11 @@ const CHUNK_SIZE: usize = 64;
 const SIMD_NEWLINE: Simd<u8, CHUNK_SIZE> = Simd::splat(b'\n');
 const SIMD_DELIM: Simd<u8, CHUNK_SIZE> = Simd::splat(b';');
 
-pub fn parse<'a>(data: &'a [u8]) -> MyHashMap<&'a [u8], Weather> {
+/// Initial station-table capacity; rounded up to a power of two and grown as
+/// needed, but sized so a typical run never has to resize.
+const INITIAL_STATIONS: usize = 4096;
+
+pub fn parse<'a>(data: &'a [u8]) -> FlatTable<'a, Weather> {
     let mut prev = 0;
     let mut pos = 0;
     let (chunks, remainder) = data.as_chunks();
@@ -41,7 +57,7 @@ pub fn parse<'a>(data: &'a [u8]) -> MyHashMap<&'a [u8], Weather> {
         .map(|chunk| chunk.simd_eq(SIMD_NEWLINE) | chunk.simd_eq(SIMD_DELIM))
         .map(Mask::to_bitmask);
 
-    let mut map = MyHashMap::<&'a [u8], Weather>::default();
+    let mut map = FlatTable::<'a, Weather>::with_capacity(INITIAL_STATIONS);
     let mut buf = [&[][..]; 128];
     let mut count = 0;
     'outer: loop {
@@ -61,19 +77,11 @@ pub fn parse<'a>(data: &'a [u8]) -> MyHashMap<&'a [u8], Weather> {
         for i in 0..count / 2 {
             let station = unsafe { buf.get_unchecked(2 * i) };
             let measurement = parse_temp(unsafe { buf.get_unchecked(2 * i + 1) });
-            map.entry(station)
-                .and_modify(|entry| {
-                    entry.total += 1;
-                    entry.min = entry.min.min(measurement);
-                    entry.max = entry.max.max(measurement);
-                    entry.sum += measurement as i64;
-                })
-                .or_insert(Weather {
-                    total: 1,
-                    min: measurement,
-                    max: measurement,
-                    sum: measurement as i64,
-                });
+            let entry = map.entry_or_default(station);
+            entry.total += 1;
+            entry.min = entry.min.min(measurement);
+            entry.max = entry.max.max(measurement);
+            entry.sum += measurement as i64;
         }
 
         // handle possible remainder